@@ -28,10 +28,13 @@ fuzz_target!(|operations: Vec<Operation>| {
                 if let Some(value) = values.remove(&(index as usize)) {
                     let colony_value = colony[index as usize];
                     assert_eq!(colony_value, value);
-                    colony.remove(index as usize);
+                    assert_eq!(colony.remove(index as usize), Some(value));
+                } else {
+                    assert_eq!(colony.remove(index as usize), None);
                 }
             }
         }
+        colony.check_invariants().unwrap();
     }
 
     for (index, value) in values {
@@ -0,0 +1,376 @@
+use core::{
+    cmp::Ordering,
+    fmt,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    ptr, slice,
+};
+
+/// # ColonyArray
+/// Fixed-capacity [Colony](crate::Colony) backed by arrays, it never allocates.
+/// ```rust
+/// # use packed_colony::ColonyArray;
+/// let mut voices: ColonyArray<f32, 2> = ColonyArray::new();
+/// let a = voices.try_insert(440.0).unwrap();
+/// let b = voices.try_insert(880.0).unwrap();
+/// assert_eq!(voices.try_insert(220.0), Err(220.0));
+/// voices.remove(a);
+/// assert!(voices.try_insert(220.0).is_ok());
+/// ```
+/// Same packed layout and O(1) operations as a [Colony](crate::Colony),
+/// suitable for audio threads and embedded targets.
+/// Ids are always less than `N`.
+/// Sort with the methods on [ColonyArray] itself. Reordering through the slice,
+/// for example with `sort_by_cached_key`, leaves ids pointing at the wrong elements.
+pub struct ColonyArray<T, const N: usize> {
+    // ID -> Member Index
+    id_to_index: [usize; N],
+    // Member Index -> ID
+    index_to_id: [usize; N],
+    // Freed IDs which can be re-used.
+    // Used as a stack.
+    freed: [usize; N],
+    freed_len: usize,
+    // Number of ids handed out so far, live or freed.
+    slots: usize,
+    // The first `len` are initialized.
+    elements: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> Default for ColonyArray<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> ColonyArray<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            id_to_index: [usize::MAX; N],
+            index_to_id: [0; N],
+            freed: [0; N],
+            freed_len: 0,
+            slots: 0,
+            elements: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub const fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Inserts `entity` and returns its id, or gives it back if the colony is full.
+    pub fn try_insert(&mut self, entity: T) -> Result<usize, T> {
+        if self.is_full() {
+            return Err(entity);
+        }
+        // Every id is either live or freed, so with no freed ids slots == len < N.
+        let id = if self.freed_len > 0 {
+            self.freed_len -= 1;
+            self.freed[self.freed_len]
+        } else {
+            self.slots += 1;
+            self.slots - 1
+        };
+        self.id_to_index[id] = self.len;
+        self.index_to_id[self.len] = id;
+        self.elements[self.len].write(entity);
+        self.len += 1;
+        Ok(id)
+    }
+
+    fn to_index(&self, id: usize) -> Option<usize> {
+        let index = *self.id_to_index.get(id).unwrap_or(&usize::MAX);
+        if index == usize::MAX {
+            return None;
+        }
+        Some(index)
+    }
+
+    /// The Index trait is also supported.
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.as_slice().get(self.to_index(id)?)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut T> {
+        let index = self.to_index(id)?;
+        self.as_mut_slice().get_mut(index)
+    }
+
+    /// Removes the element with `id` and returns it.
+    /// Idempotent, calling with invalid id will do nothing and return `None`.
+    pub fn remove(&mut self, id: usize) -> Option<T> {
+        let index = self.to_index(id)?;
+        let last_index = self.len - 1;
+        let last_id = self.index_to_id[last_index];
+
+        // When removing the last member, last_id == id, so this must come first.
+        self.id_to_index[last_id] = index;
+        self.id_to_index[id] = usize::MAX;
+        self.index_to_id[index] = last_id;
+        self.freed[self.freed_len] = id;
+        self.freed_len += 1;
+
+        self.elements.swap(index, last_index);
+        self.len = last_index;
+        // SAFETY: The element at `last_index` was initialized, and is now past `len`.
+        Some(unsafe { self.elements[last_index].assume_init_read() })
+    }
+
+    pub fn clear(&mut self) {
+        let elements: *mut [T] = self.as_mut_slice();
+        // Forget the elements first, so a panicking drop leaks instead of double dropping.
+        self.len = 0;
+        self.freed_len = 0;
+        self.id_to_index[..self.slots].fill(usize::MAX);
+        self.slots = 0;
+        // SAFETY: The elements were initialized and are no longer reachable.
+        unsafe { ptr::drop_in_place(elements) };
+    }
+
+    /// Sorts the elements, ids keep referring to the same elements.
+    /// Shadows [slice::sort], which would leave ids pointing at the wrong elements.
+    /// This sort is stable and does not allocate.
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_by(Ord::cmp);
+    }
+
+    /// Like [ColonyArray::sort], but may not preserve the order of equal elements.
+    pub fn sort_unstable(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_unstable_by(Ord::cmp);
+    }
+
+    /// Sorts the elements with a comparator, ids keep referring to the same elements.
+    /// This sort is stable and does not allocate.
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        // Breaking ties by index makes the unstable sort stable.
+        self.sort_unstable_by_index(|elements, a, b| {
+            compare(&elements[a], &elements[b]).then(a.cmp(&b))
+        });
+    }
+
+    /// Sorts the elements with a key extraction function, see [ColonyArray::sort_by].
+    pub fn sort_by_key<K, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    /// Like [ColonyArray::sort_by], but may not preserve the order of equal elements.
+    pub fn sort_unstable_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        self.sort_unstable_by_index(|elements, a, b| compare(&elements[a], &elements[b]));
+    }
+
+    /// Like [ColonyArray::sort_by_key], but may not preserve the order of equal elements.
+    pub fn sort_unstable_by_key<K, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_unstable_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    // Sorts a stack buffer of indices, then moves the element at order[i] to
+    // index i, following each cycle of the permutation with swaps. No user
+    // code runs while moving, so a panicking comparator leaves the colony untouched.
+    fn sort_unstable_by_index<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&[T], usize, usize) -> Ordering,
+    {
+        let mut order = [0; N];
+        let order = &mut order[..self.len];
+        for (index, slot) in order.iter_mut().enumerate() {
+            *slot = index;
+        }
+        let elements = self.as_slice();
+        order.sort_unstable_by(|a, b| compare(elements, *a, *b));
+        for start in 0..order.len() {
+            let mut current = start;
+            while order[current] != start {
+                let next = order[current];
+                self.elements.swap(current, next);
+                self.index_to_id.swap(current, next);
+                self.id_to_index[self.index_to_id[current]] = current;
+                self.id_to_index[self.index_to_id[next]] = next;
+                order[current] = current;
+                current = next;
+            }
+            order[current] = current;
+        }
+    }
+
+    pub const fn as_slice(&self) -> &[T] {
+        // SAFETY: The first `len` elements are initialized.
+        unsafe { slice::from_raw_parts(self.elements.as_ptr().cast(), self.len) }
+    }
+
+    pub const fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: The first `len` elements are initialized.
+        unsafe { slice::from_raw_parts_mut(self.elements.as_mut_ptr().cast(), self.len) }
+    }
+}
+
+impl<T, const N: usize> Drop for ColonyArray<T, N> {
+    fn drop(&mut self) {
+        // SAFETY: The first `len` elements are initialized.
+        unsafe { ptr::drop_in_place(self.as_mut_slice()) };
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for ColonyArray<T, N> {
+    fn clone(&self) -> Self {
+        let mut clone = Self::new();
+        for (index, element) in self.as_slice().iter().enumerate() {
+            clone.elements[index].write(element.clone());
+            // Keep `len` up to date so a panicking clone drops what was written.
+            clone.len = index + 1;
+        }
+        clone.id_to_index = self.id_to_index;
+        clone.index_to_id = self.index_to_id;
+        clone.freed = self.freed;
+        clone.freed_len = self.freed_len;
+        clone.slots = self.slots;
+        clone
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for ColonyArray<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ColonyArray")
+            .field("id_to_index", &&self.id_to_index[..self.slots])
+            .field("index_to_id", &&self.index_to_id[..self.len])
+            .field("freed", &&self.freed[..self.freed_len])
+            .field("elements", &self.as_slice())
+            .finish()
+    }
+}
+
+impl<T, const N: usize> core::ops::Index<usize> for ColonyArray<T, N> {
+    type Output = T;
+
+    fn index(&self, id: usize) -> &Self::Output {
+        self.as_slice().index(self.id_to_index[id])
+    }
+}
+
+impl<T, const N: usize> core::ops::IndexMut<usize> for ColonyArray<T, N> {
+    fn index_mut(&mut self, id: usize) -> &mut Self::Output {
+        let index = self.id_to_index[id];
+        self.as_mut_slice().index_mut(index)
+    }
+}
+
+impl<T, const N: usize> Deref for ColonyArray<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for ColonyArray<T, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{rc::Rc, vec::Vec};
+
+    #[test]
+    fn array() {
+        let mut world: ColonyArray<&str, 3> = ColonyArray::new();
+        let a = world.try_insert("A").unwrap();
+        let b = world.try_insert("B").unwrap();
+        let c = world.try_insert("C").unwrap();
+        assert!(world.is_full());
+        assert_eq!(world.try_insert("D"), Err("D"));
+        assert_eq!(world.remove(a), Some("A"));
+        assert_eq!(world.remove(a), None);
+        assert_eq!(world.get(a), None);
+        assert_eq!(world[b], "B");
+        assert_eq!(world[c], "C");
+        assert_eq!(world.as_slice(), ["C", "B"]);
+        assert_eq!(world.remove(b), Some("B"));
+        let d = world.try_insert("D").unwrap();
+        assert!(d < 3);
+        assert_eq!(world[d], "D");
+        let clone = world.clone();
+        world.clear();
+        assert!(world.is_empty());
+        assert_eq!(world.get(c), None);
+        assert_eq!(clone[c], "C");
+        assert_eq!(clone[d], "D");
+    }
+
+    #[test]
+    fn drops() {
+        let rc = Rc::new(());
+        let mut world: ColonyArray<Rc<()>, 4> = ColonyArray::new();
+        let ids: Vec<usize> = (0..4)
+            .map(|_| world.try_insert(rc.clone()).unwrap())
+            .collect();
+        assert_eq!(Rc::strong_count(&rc), 5);
+        drop(world.remove(ids[1]));
+        assert_eq!(Rc::strong_count(&rc), 4);
+        let clone = world.clone();
+        assert_eq!(Rc::strong_count(&rc), 7);
+        world.clear();
+        assert_eq!(Rc::strong_count(&rc), 4);
+        drop(clone);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn sort() {
+        let mut world: ColonyArray<i32, 8> = ColonyArray::new();
+        let values = [5, 3, 9, 1, 7, 3, 0, 8];
+        let ids: Vec<usize> = values
+            .iter()
+            .map(|x| world.try_insert(*x).unwrap())
+            .collect();
+        world.remove(ids[2]);
+        world.sort();
+        assert_eq!(world.as_slice(), [0, 1, 3, 3, 5, 7, 8]);
+        for (id, value) in ids.iter().zip(values) {
+            assert_eq!(world.get(*id), (*id != ids[2]).then_some(&value));
+        }
+        // Equal elements keep their order.
+        world.sort_by_key(|x| *x / 4);
+        assert_eq!(world.as_slice(), [0, 1, 3, 3, 5, 7, 8]);
+        assert_eq!(world.index_to_id[2..4], [ids[1], ids[5]]);
+        world.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(world.as_slice(), [8, 7, 5, 3, 3, 1, 0]);
+        world.sort_unstable_by_key(|x| *x % 5);
+        world.sort_unstable();
+        world.sort_by(|a, b| b.cmp(a));
+        assert_eq!(world.as_slice(), [8, 7, 5, 3, 3, 1, 0]);
+        for (id, value) in ids.iter().zip(values) {
+            assert_eq!(world.get(*id), (*id != ids[2]).then_some(&value));
+        }
+        let id = world.try_insert(4).unwrap();
+        assert_eq!(world[id], 4);
+    }
+}
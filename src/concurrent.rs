@@ -0,0 +1,244 @@
+//! Sharded [Colony] for inserting and removing from many threads at once.
+
+use crate::Colony;
+use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// # ConcurrentColony
+/// A [Colony] split into shards, each behind its own lock.
+/// ```rust
+/// # use packed_colony::concurrent::ConcurrentColony;
+/// let mut world = ConcurrentColony::new();
+/// std::thread::scope(|scope| {
+///     for thread in 0..4 {
+///         let world = &world;
+///         scope.spawn(move || {
+///             let id = world.insert(thread);
+///             assert_eq!(world.get(id), Some(thread));
+///         });
+///     }
+/// });
+/// // Single-threaded phase, no locking.
+/// for slice in world.slices_mut() {
+///     for x in slice {
+///         *x *= 2;
+///     }
+/// }
+/// ```
+/// Inserts are spread across the shards, so threads rarely contend for the same lock.
+/// Lookups and removals only lock the shard the id belongs to.
+/// Methods taking `&mut self` need no locking, and expose each shard's packed
+/// slice for single-threaded iteration phases.
+pub struct ConcurrentColony<T> {
+    shards: Box<[RwLock<Colony<T>>]>,
+    next_shard: AtomicUsize,
+}
+
+impl<T> Default for ConcurrentColony<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ConcurrentColony<T> {
+    /// Constructs a new, empty ConcurrentColony<T> with a few shards per available thread.
+    pub fn new() -> Self {
+        let threads = std::thread::available_parallelism().map_or(4, |threads| threads.get());
+        Self::with_shards(threads * 4)
+    }
+
+    /// # Panics
+    /// Panics if `shards` is zero.
+    pub fn with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "ConcurrentColony needs at least one shard");
+        Self {
+            shards: (0..shards).map(|_| RwLock::new(Colony::new())).collect(),
+            next_shard: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    // The shard is in the low digits of the id, the id within the shard in the rest.
+    fn split(&self, id: usize) -> (usize, usize) {
+        (id % self.shards.len(), id / self.shards.len())
+    }
+
+    // A panic while a lock is held cannot leave a Colony inconsistent,
+    // so poisoning is ignored.
+    fn read(&self, shard: usize) -> RwLockReadGuard<'_, Colony<T>> {
+        self.shards[shard]
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self, shard: usize) -> RwLockWriteGuard<'_, Colony<T>> {
+        self.shards[shard]
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn insert(&self, entity: T) -> usize {
+        let shard = self.next_shard.fetch_add(1, Ordering::Relaxed) % self.shards.len();
+        let local_id = self.write(shard).insert(entity);
+        local_id * self.shards.len() + shard
+    }
+
+    pub fn contains_id(&self, id: usize) -> bool {
+        let (shard, local_id) = self.split(id);
+        self.read(shard).get(local_id).is_some()
+    }
+
+    pub fn get(&self, id: usize) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.with(id, T::clone)
+    }
+
+    /// Calls `f` with the element for `id`, holding its shard's read lock.
+    pub fn with<R, F>(&self, id: usize, f: F) -> Option<R>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let (shard, local_id) = self.split(id);
+        self.read(shard).get(local_id).map(f)
+    }
+
+    /// Calls `f` with the element for `id`, holding its shard's write lock.
+    pub fn with_mut<R, F>(&self, id: usize, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        let (shard, local_id) = self.split(id);
+        self.write(shard).get_mut(local_id).map(f)
+    }
+
+    /// Removes the element with `id` and returns it.
+    /// Idempotent, calling with invalid id will do nothing and return `None`.
+    pub fn remove(&self, id: usize) -> Option<T> {
+        let (shard, local_id) = self.split(id);
+        self.write(shard).remove(local_id)
+    }
+
+    /// Number of elements across all shards, which may change as soon as it is returned.
+    pub fn len(&self) -> usize {
+        (0..self.shards.len())
+            .map(|shard| self.read(shard).len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut T> {
+        let (shard, local_id) = self.split(id);
+        self.shards[shard]
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_mut(local_id)
+    }
+
+    fn colonies_mut(&mut self) -> impl Iterator<Item = &mut Colony<T>> {
+        self.shards
+            .iter_mut()
+            .map(|shard| shard.get_mut().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// The packed slice of each shard.
+    pub fn slices_mut(&mut self) -> impl Iterator<Item = &mut [T]> {
+        self.colonies_mut().map(|colony| colony.as_mut_slice())
+    }
+
+    /// Iterates over `(id, &mut element)`, one shard after another in packed order.
+    pub fn iter_mut_with_ids(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        let shards = self.shards.len();
+        self.colonies_mut()
+            .enumerate()
+            .flat_map(move |(shard, colony)| {
+                let index = &colony.index;
+                colony
+                    .elements
+                    .iter_mut()
+                    .enumerate()
+                    .map(move |(i, element)| (index.to_id(i) * shards + shard, element))
+            })
+    }
+
+    pub fn clear(&mut self) {
+        self.colonies_mut().for_each(Colony::clear);
+    }
+
+    /// Consumes the colony, returning the shards.
+    /// The id of an element in shard `s` with local id `l` is `l * shard_count + s`.
+    pub fn into_shards(self) -> Vec<Colony<T>> {
+        self.shards
+            .into_vec()
+            .into_iter()
+            .map(|shard| shard.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent() {
+        let mut world = ConcurrentColony::with_shards(3);
+        let ids: Vec<Vec<usize>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|thread| {
+                    let world = &world;
+                    scope.spawn(move || {
+                        let ids: Vec<usize> =
+                            (0..100).map(|x| world.insert(thread * 1000 + x)).collect();
+                        ids
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        // Removing in a separate phase, so freed ids are not re-used by other threads.
+        std::thread::scope(|scope| {
+            for (thread, ids) in ids.iter().enumerate() {
+                let world = &world;
+                scope.spawn(move || {
+                    for (x, id) in ids.iter().enumerate().step_by(2) {
+                        assert_eq!(world.remove(*id), Some(thread * 1000 + x));
+                    }
+                });
+            }
+        });
+        assert_eq!(world.len(), 200);
+        for (thread, ids) in ids.iter().enumerate() {
+            for (x, id) in ids.iter().enumerate() {
+                let expected = (x % 2 == 1).then_some(thread * 1000 + x);
+                assert_eq!(world.get(*id), expected);
+                assert_eq!(world.contains_id(*id), x % 2 == 1);
+            }
+        }
+        assert_eq!(
+            world.with_mut(ids[0][1], |x| std::mem::replace(x, 7)),
+            Some(1)
+        );
+        for (id, x) in world.iter_mut_with_ids() {
+            if id == ids[0][1] {
+                assert_eq!(*x, 7);
+            }
+            *x += 1;
+        }
+        assert_eq!(world.get_mut(ids[0][1]).copied(), Some(8));
+        let total: usize = world.slices_mut().map(|slice| slice.len()).sum();
+        assert_eq!(total, 200);
+        assert_eq!(world.into_shards().len(), 3);
+    }
+}
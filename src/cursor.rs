@@ -0,0 +1,86 @@
+use crate::{Colony, IndexType};
+
+/// Walks a [Colony] in packed order, allowing the current element to be removed.
+/// Created by [Colony::cursor_mut].
+/// ```rust
+/// # use packed_colony::Colony;
+/// let mut healths = Colony::new();
+/// healths.extend([3, 1, 2, 1]);
+/// let mut cursor = healths.cursor_mut();
+/// while let Some(health) = cursor.current() {
+///     *health -= 1;
+///     if *health == 0 {
+///         cursor.remove_current();
+///     } else {
+///         cursor.advance();
+///     }
+/// }
+/// assert_eq!(healths.len(), 2);
+/// ```
+pub struct CursorMut<'a, T, I = usize> {
+    colony: &'a mut Colony<T, I>,
+    index: usize,
+}
+
+impl<'a, T, I: IndexType> CursorMut<'a, T, I> {
+    pub(crate) fn new(colony: &'a mut Colony<T, I>) -> Self {
+        Self { colony, index: 0 }
+    }
+
+    /// The current element, `None` once every element has been visited.
+    pub fn current(&mut self) -> Option<&mut T> {
+        self.colony.elements.get_mut(self.index)
+    }
+
+    pub fn current_id(&self) -> Option<usize> {
+        (self.index < self.colony.elements.len()).then(|| self.colony.index.to_id(self.index))
+    }
+
+    /// Moves on to the next element.
+    pub fn advance(&mut self) {
+        if self.index < self.colony.elements.len() {
+            self.index += 1;
+        }
+    }
+
+    /// Removes the current element and returns it.
+    /// The last element is swapped into its place and becomes the current element,
+    /// so do not [CursorMut::advance] afterwards.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let id = self.current_id()?;
+        self.colony.remove(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Colony;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn cursor() {
+        let mut world = Colony::new();
+        let ids = world.extend_with_ids(0..10);
+        let mut visited = Vec::new();
+        let mut cursor = world.cursor_mut();
+        while let Some(id) = cursor.current_id() {
+            let value = *cursor.current().unwrap();
+            visited.push(value);
+            if value % 2 == 0 {
+                assert_eq!(cursor.remove_current(), Some(value));
+            } else {
+                *cursor.current().unwrap() *= 10;
+                assert_eq!(ids[value], id);
+                cursor.advance();
+            }
+        }
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.remove_current(), None);
+        cursor.advance();
+        visited.sort();
+        assert_eq!(visited, (0..10).collect::<Vec<_>>());
+        for (value, id) in ids.iter().enumerate() {
+            assert_eq!(world.get(*id), (value % 2 == 1).then_some(&(value * 10)));
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use core::fmt::Debug;
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for usize {}
+    impl Sealed for u32 {}
+}
+
+/// Integer type used to store ids and indices in a [ColonyIndex](crate::ColonyIndex).
+///
+/// `usize` is the default. `u32` halves the memory used by the lookup tables
+/// on 64-bit targets, but limits a colony to `u32::MAX` ids (about 4 billion),
+/// as `u32::MAX` itself marks removed ids.
+/// Ids are always passed in and handed out as `usize`.
+pub trait IndexType: Copy + Eq + Debug + Send + Sync + sealed::Sealed + 'static {
+    /// Marks a removed id in `id_to_index`, never a valid id or index.
+    const MAX: Self;
+
+    /// Checked conversion, `None` if `value` does not fit or is [IndexType::MAX].
+    fn try_from_usize(value: usize) -> Option<Self>;
+
+    fn to_usize(self) -> usize;
+
+    /// # Panics
+    /// Panics if `value` does not fit or is [IndexType::MAX].
+    fn from_usize(value: usize) -> Self {
+        match Self::try_from_usize(value) {
+            Some(value) => value,
+            None => panic!("colony id space exhausted"),
+        }
+    }
+}
+
+impl IndexType for usize {
+    const MAX: Self = usize::MAX;
+
+    fn try_from_usize(value: usize) -> Option<Self> {
+        (value != usize::MAX).then_some(value)
+    }
+
+    fn to_usize(self) -> usize {
+        self
+    }
+}
+
+impl IndexType for u32 {
+    const MAX: Self = u32::MAX;
+
+    fn try_from_usize(value: usize) -> Option<Self> {
+        u32::try_from(value).ok().filter(|value| *value != u32::MAX)
+    }
+
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
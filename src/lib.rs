@@ -1,23 +1,69 @@
-use std::{
+#![no_std]
+
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+use alloc::vec::Vec;
+use core::{
+    cmp::Ordering,
     ops::{Deref, DerefMut},
-    vec::Vec,
 };
 
-#[derive(Default, Debug, Clone)]
+mod array;
+#[cfg(feature = "std")]
+pub mod concurrent;
+mod cursor;
+mod index_type;
+#[cfg(feature = "rayon")]
+mod rayon_impl;
+mod secondary;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod zip;
+pub use array::ColonyArray;
+pub use cursor::CursorMut;
+pub use index_type::IndexType;
+pub use secondary::SecondaryColony;
+pub use zip::{ZipById, ZipByIdMut};
+
+#[derive(Debug, Clone)]
 /// Can be used to implement your own custom Colony.
 /// Most users should just use [Colony]
-pub struct ColonyIndex {
+pub struct ColonyIndex<I = usize> {
     // ID -> Member Index
-    id_to_index: Vec<usize>,
+    id_to_index: Vec<I>,
     // Member Index -> ID
-    index_to_id: Vec<usize>,
+    index_to_id: Vec<I>,
     // Freed IDs which can be re-used.
     // Used as a stack.
-    freed: Vec<usize>,
+    freed: Vec<I>,
+}
+
+impl Default for ColonyIndex {
+    fn default() -> Self {
+        Self::new_with_index_type()
+    }
 }
 
 impl ColonyIndex {
     pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_index_type(capacity)
+    }
+}
+
+impl<I: IndexType> ColonyIndex<I> {
+    /// Like [ColonyIndex::default], for any [IndexType].
+    pub const fn new_with_index_type() -> Self {
+        Self {
+            id_to_index: Vec::new(),
+            index_to_id: Vec::new(),
+            freed: Vec::new(),
+        }
+    }
+
+    /// Like [ColonyIndex::with_capacity], for any [IndexType].
+    pub fn with_capacity_and_index_type(capacity: usize) -> Self {
         Self {
             id_to_index: Vec::with_capacity(capacity),
             index_to_id: Vec::with_capacity(capacity),
@@ -25,28 +71,151 @@ impl ColonyIndex {
         }
     }
 
+    /// # Panics
+    /// Panics if the id space of `I` is exhausted.
     pub fn insert(&mut self, index: usize) -> usize {
         if let Some(id) = self.freed.pop() {
-            self.id_to_index[id] = index;
-            self.index_to_id[index as usize] = id;
-            return id;
+            self.id_to_index[id.to_usize()] = I::from_usize(index);
+            self.index_to_id.push(id);
+            return id.to_usize();
         }
-        let id = self.id_to_index.len();
-        self.id_to_index.push(index);
+        // There are never more members than ids, so if the id fits the index does.
+        let id = I::from_usize(self.id_to_index.len());
+        self.id_to_index.push(I::from_usize(index));
         self.index_to_id.push(id);
-        id
+        id.to_usize()
+    }
+
+    /// Id the next call to [ColonyIndex::insert] will hand out.
+    pub fn next_id(&self) -> usize {
+        match self.freed.last() {
+            Some(id) => id.to_usize(),
+            None => self.id_to_index.len(),
+        }
     }
 
     pub fn to_index_unchecked(&self, id: usize) -> usize {
-        self.id_to_index[id]
+        self.id_to_index[id].to_usize()
+    }
+
+    /// Like [ColonyIndex::to_index_unchecked], without the bounds check.
+    /// # Safety
+    /// `id` must be less than [ColonyIndex::slot_count].
+    pub unsafe fn get_unchecked(&self, id: usize) -> usize {
+        unsafe { self.id_to_index.get_unchecked(id).to_usize() }
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.to_index(id).is_some()
+    }
+
+    /// Id of the member at `index`.
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn to_id(&self, index: usize) -> usize {
+        self.index_to_id[index].to_usize()
     }
 
     pub fn to_index(&self, id: usize) -> Option<usize> {
-        let index = *self.id_to_index.get(id).unwrap_or(&std::usize::MAX);
-        if index == std::usize::MAX {
+        let index = *self.id_to_index.get(id).unwrap_or(&I::MAX);
+        if index == I::MAX {
             return None;
         }
-        Some(index)
+        Some(index.to_usize())
+    }
+
+    /// Swaps the members at indices `a` and `b`.
+    /// # Panics
+    /// Panics if `a` or `b` are out of bounds.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.index_to_id.swap(a, b);
+        self.id_to_index[self.index_to_id[a].to_usize()] = I::from_usize(a);
+        self.id_to_index[self.index_to_id[b].to_usize()] = I::from_usize(b);
+    }
+
+    /// Reserves capacity for at least `additional` more members.
+    pub fn reserve(&mut self, additional: usize) {
+        self.index_to_id.reserve(additional);
+        // Freed ids are re-used before new ones are handed out.
+        self.id_to_index
+            .reserve(additional.saturating_sub(self.freed.len()));
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.id_to_index.shrink_to_fit();
+        self.index_to_id.shrink_to_fit();
+        self.freed.shrink_to_fit();
+    }
+
+    /// Number of ids handed out so far, live or freed.
+    pub fn slot_count(&self) -> usize {
+        self.id_to_index.len()
+    }
+
+    /// Number of freed ids waiting to be re-used.
+    pub fn freed_count(&self) -> usize {
+        self.freed.len()
+    }
+
+    /// Re-assigns ids so each member's id is its index, forgetting freed ids
+    /// and shrinking the lookup tables.
+    /// Returns the new id for each old id, `None` for freed ids.
+    pub fn compact(&mut self) -> Vec<Option<usize>> {
+        let remap = self
+            .id_to_index
+            .iter()
+            .map(|index| (*index != I::MAX).then(|| index.to_usize()))
+            .collect();
+        self.id_to_index.clear();
+        self.freed.clear();
+        for (index, id) in self.index_to_id.iter_mut().enumerate() {
+            *id = I::from_usize(index);
+            self.id_to_index.push(*id);
+        }
+        self.shrink_to_fit();
+        remap
+    }
+
+    /// Verifies the lookup tables are mutually consistent:
+    /// every member's id maps back to its index, every freed id maps to nothing,
+    /// and every id handed out is either a member or freed, exactly once.
+    /// Runs in O(n) and allocates, meant for tests and debugging.
+    pub fn check_invariants(&self) -> Result<(), &'static str> {
+        let slots = self.id_to_index.len();
+        if self.index_to_id.len() + self.freed.len() != slots {
+            return Err("id count does not match member and freed counts");
+        }
+        for (index, id) in self.index_to_id.iter().enumerate() {
+            match self.id_to_index.get(id.to_usize()) {
+                Some(mapped) if mapped.to_usize() == index => {}
+                _ => return Err("member id does not map back to its index"),
+            }
+        }
+        let mut seen = alloc::vec![false; slots];
+        for id in &self.freed {
+            let id = id.to_usize();
+            if id >= slots || self.id_to_index[id] != I::MAX {
+                return Err("freed id is out of range or still maps to a member");
+            }
+            if core::mem::replace(&mut seen[id], true) {
+                return Err("freed id is duplicated");
+            }
+        }
+        Ok(())
+    }
+
+    /// Forgets all ids, keeping the allocated capacity.
+    pub fn clear(&mut self) {
+        self.id_to_index.clear();
+        self.index_to_id.clear();
+        self.freed.clear();
+    }
+
+    /// Forgets all ids, yielding the ids of the members in index order.
+    pub fn drain(&mut self) -> impl ExactSizeIterator<Item = usize> {
+        self.id_to_index.clear();
+        self.freed.clear();
+        self.index_to_id.drain(..).map(|id| id.to_usize())
     }
 
     // Removal is always where they get you.
@@ -70,16 +239,15 @@ impl ColonyIndex {
     // id_to_index: [2,0,1,1]
     //     elements: [A,D,C]
     pub fn remove(&mut self, target_id: usize, last_index: usize) -> Option<usize> {
-        let target_index = *self.id_to_index.get(target_id).unwrap_or(&std::usize::MAX);
-        if target_index == std::usize::MAX {
-            return None;
-        }
+        let target_index = self.to_index(target_id)?;
         let last_id = self.index_to_id[last_index];
 
-        self.id_to_index[target_id] = std::usize::MAX;
-        self.id_to_index[last_id] = target_index;
+        // When removing the last member, last_id == target_id, so this must come first.
+        self.id_to_index[last_id.to_usize()] = I::from_usize(target_index);
+        self.id_to_index[target_id] = I::MAX;
         self.index_to_id[target_index] = last_id;
-        self.freed.push(target_id);
+        self.index_to_id.truncate(last_index);
+        self.freed.push(I::from_usize(target_id));
         Some(target_index)
     }
 }
@@ -135,17 +303,24 @@ impl ColonyIndex {
 /// During removal, the removed element is swapped for the last
 /// element in members, and the lookup tables are updated.
 /// This naturally keeps all the data tightly packed.
-pub struct Colony<T> {
-    index: ColonyIndex,
+/// ### Index Type
+/// The lookup tables store `usize` by default.
+/// On 64-bit targets `u32` halves their memory and improves lookup cache behavior,
+/// but limits the colony to about 4 billion ids, inserting past that panics.
+/// ```rust
+/// # use packed_colony::Colony;
+/// let mut particles: Colony<[f32; 3], u32> = Colony::new_with_index_type();
+/// let spark = particles.insert([0.0, 1.0, 0.0]);
+/// assert_eq!(particles[spark], [0.0, 1.0, 0.0]);
+/// ```
+pub struct Colony<T, I = usize> {
+    index: ColonyIndex<I>,
     elements: Vec<T>,
 }
 
 impl<T> Default for Colony<T> {
     fn default() -> Self {
-        Self {
-            index: ColonyIndex::default(),
-            elements: Vec::new(),
-        }
+        Self::new_with_index_type()
     }
 }
 
@@ -158,8 +333,23 @@ impl<T> Colony<T> {
     /// # Panics
     /// Panics if the new capacity exceeds isize::MAX bytes.
     pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_index_type(capacity)
+    }
+}
+
+impl<T, I: IndexType> Colony<T, I> {
+    /// Like [Colony::new], for any [IndexType].
+    pub const fn new_with_index_type() -> Self {
         Self {
-            index: ColonyIndex::with_capacity(capacity),
+            index: ColonyIndex::new_with_index_type(),
+            elements: Vec::new(),
+        }
+    }
+
+    /// Like [Colony::with_capacity], for any [IndexType].
+    pub fn with_capacity_and_index_type(capacity: usize) -> Self {
+        Self {
+            index: ColonyIndex::with_capacity_and_index_type(capacity),
             elements: Vec::with_capacity(capacity),
         }
     }
@@ -167,9 +357,53 @@ impl<T> Colony<T> {
     pub fn insert(&mut self, entity: T) -> usize {
         let id = self.index.insert(self.elements.len());
         self.elements.push(entity);
+        self.validate();
         id
     }
 
+    /// Inserts every element of `iter`, returning their ids in order.
+    pub fn extend_with_ids<It>(&mut self, iter: It) -> Vec<usize>
+    where
+        It: IntoIterator<Item = T>,
+    {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        iter.map(|element| self.insert(element)).collect()
+    }
+
+    /// Inserts the element returned by `f`, which is passed the id it will be stored under.
+    /// ```rust
+    /// # use packed_colony::Colony;
+    /// struct Node {
+    ///     id: usize,
+    /// }
+    /// let mut graph = Colony::new();
+    /// let id = graph.insert_with(|id| Node { id });
+    /// assert_eq!(graph[id].id, id);
+    /// ```
+    pub fn insert_with<F>(&mut self, f: F) -> usize
+    where
+        F: FnOnce(usize) -> T,
+    {
+        let element = f(self.index.next_id());
+        self.insert(element)
+    }
+
+    /// Returns the element for `id`, or inserts the element returned by `f` if there is none.
+    /// An inserted element gets a new id, which is passed to `f`,
+    /// so the id of the returned element is returned alongside it.
+    pub fn get_or_insert_with<F>(&mut self, id: usize, f: F) -> (usize, &mut T)
+    where
+        F: FnOnce(usize) -> T,
+    {
+        let id = match self.index.to_index(id) {
+            Some(_) => id,
+            None => self.insert_with(f),
+        };
+        let index = self.index.to_index_unchecked(id);
+        (id, &mut self.elements[index])
+    }
+
     /// The Index trait is also supported.
     pub fn get(&self, id: usize) -> Option<&T> {
         if let Some(index) = self.index.to_index(id) {
@@ -185,16 +419,306 @@ impl<T> Colony<T> {
         None
     }
 
-    /// Idempotent, calling with invalid id will do nothing.
-    pub fn remove(&mut self, id: usize) {
-        if let Some(index) = self.index.remove(id, self.elements.len() - 1) {
-            self.elements.swap_remove(index);
+    /// Whether `id` refers to an element.
+    /// Lets validation be hoisted out of hot loops using [Colony::get_unchecked].
+    pub fn contains_id(&self, id: usize) -> bool {
+        self.index.contains(id)
+    }
+
+    /// Like [Colony::get], without checking that `id` is valid.
+    /// # Safety
+    /// `id` must refer to an element, as reported by [Colony::contains_id].
+    /// Calling with a removed id, or one never handed out, is undefined behavior.
+    pub unsafe fn get_unchecked(&self, id: usize) -> &T {
+        unsafe {
+            let index = self.index.get_unchecked(id);
+            self.elements.get_unchecked(index)
+        }
+    }
+
+    /// Like [Colony::get_mut], without checking that `id` is valid.
+    /// # Safety
+    /// `id` must refer to an element, as reported by [Colony::contains_id].
+    /// Calling with a removed id, or one never handed out, is undefined behavior.
+    pub unsafe fn get_unchecked_mut(&mut self, id: usize) -> &mut T {
+        unsafe {
+            let index = self.index.get_unchecked(id);
+            self.elements.get_unchecked_mut(index)
+        }
+    }
+
+    /// Removes the element with `id` and returns it.
+    /// Idempotent, calling with invalid id will do nothing and return `None`.
+    pub fn remove(&mut self, id: usize) -> Option<T> {
+        self.remove_with(id, |_, _| {})
+    }
+
+    /// Like [Colony::remove], but calls `relocated(moved_id, new_index)` when the
+    /// last element is swapped into the place of the removed one.
+    /// Lets external mirrors of the packed slice, such as GPU buffers, follow along.
+    /// ```rust
+    /// # use packed_colony::Colony;
+    /// let mut world = Colony::new();
+    /// let a = world.insert("A");
+    /// let b = world.insert("B");
+    /// world.remove_with(a, |moved_id, new_index| {
+    ///     assert_eq!((moved_id, new_index), (b, 0));
+    /// });
+    /// ```
+    pub fn remove_with<F>(&mut self, id: usize, relocated: F) -> Option<T>
+    where
+        F: FnOnce(usize, usize),
+    {
+        // An empty colony has no last index, and nothing to remove.
+        let last_index = self.elements.len().checked_sub(1)?;
+        let index = self.index.remove(id, last_index)?;
+        let element = self.elements.swap_remove(index);
+        self.validate();
+        if index < self.elements.len() {
+            relocated(self.index.to_id(index), index);
+        }
+        Some(element)
+    }
+
+    /// Iterates over `(id, &a, &b)` for every id present in both colonies,
+    /// for colonies whose elements are keyed by the same ids.
+    /// The smaller colony is iterated in packed order, and the other looked up.
+    /// ```rust
+    /// # use packed_colony::Colony;
+    /// let mut positions = Colony::new();
+    /// let mut sprites = Colony::new();
+    /// let player = positions.insert((0, 0));
+    /// sprites.insert("player.png");
+    /// positions.insert((5, 5));
+    /// for (id, _position, sprite) in positions.zip_by_id(&sprites) {
+    ///     assert_eq!((id, sprite), (player, &"player.png"));
+    /// }
+    /// ```
+    pub fn zip_by_id<'a, U, J: IndexType>(
+        &'a self,
+        other: &'a Colony<U, J>,
+    ) -> ZipById<'a, T, U, I, J> {
+        ZipById::new(self, other)
+    }
+
+    /// Like [Colony::zip_by_id], yielding `(id, &mut a, &mut b)`.
+    pub fn zip_by_id_mut<'a, U, J: IndexType>(
+        &'a mut self,
+        other: &'a mut Colony<U, J>,
+    ) -> ZipByIdMut<'a, T, U, I, J> {
+        ZipByIdMut::new(self, other)
+    }
+
+    /// Cursor over the elements in packed order, which can remove the current element.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T, I> {
+        CursorMut::new(self)
+    }
+
+    /// Retains only the elements specified by the predicate.
+    /// Removed elements are swapped out in a single pass, so the order of
+    /// the remaining elements is not preserved.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        self.retain_with_ids(|_, element| f(element));
+    }
+
+    /// Like [Colony::retain], but the predicate is also passed the id of each element.
+    pub fn retain_with_ids<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, &mut T) -> bool,
+    {
+        let mut index = 0;
+        while index < self.elements.len() {
+            let id = self.index.to_id(index);
+            if f(id, &mut self.elements[index]) {
+                index += 1;
+            } else {
+                // The last element is swapped into `index`, visit it next.
+                self.remove(id);
+            }
         }
     }
 
     pub fn clear(&mut self) {
-        self.index = ColonyIndex::default();
+        self.index.clear();
         self.elements.clear();
+        self.validate();
+    }
+
+    /// Sorts the elements, ids keep referring to the same elements.
+    /// Shadows [slice::sort], which would leave ids pointing at the wrong elements.
+    /// This sort is stable.
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_by(Ord::cmp);
+    }
+
+    /// Like [Colony::sort], but may not preserve the order of equal elements.
+    pub fn sort_unstable(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_unstable_by(Ord::cmp);
+    }
+
+    /// Sorts the elements with a comparator, ids keep referring to the same elements.
+    /// Sorting through [Colony::as_mut_slice] instead would leave ids pointing at the wrong elements.
+    /// This sort is stable.
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut order: Vec<usize> = (0..self.elements.len()).collect();
+        order.sort_by(|a, b| compare(&self.elements[*a], &self.elements[*b]));
+        self.permute(order);
+    }
+
+    /// Sorts the elements with a key extraction function, see [Colony::sort_by].
+    pub fn sort_by_key<K, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    /// Like [Colony::sort_by_key], but calls `f` only once per element.
+    /// Shadows [slice::sort_by_cached_key]. This sort is stable.
+    pub fn sort_by_cached_key<K, F>(&mut self, f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        let keys: Vec<K> = self.elements.iter().map(f).collect();
+        let mut order: Vec<usize> = (0..self.elements.len()).collect();
+        order.sort_by(|a, b| keys[*a].cmp(&keys[*b]));
+        self.permute(order);
+    }
+
+    /// Like [Colony::sort_by], but may not preserve the order of equal elements.
+    pub fn sort_unstable_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut order: Vec<usize> = (0..self.elements.len()).collect();
+        order.sort_unstable_by(|a, b| compare(&self.elements[*a], &self.elements[*b]));
+        self.permute(order);
+    }
+
+    /// Like [Colony::sort_by_key], but may not preserve the order of equal elements.
+    pub fn sort_unstable_by_key<K, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_unstable_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    // Moves the element at index order[i] to index i, following each cycle of
+    // the permutation with swaps. No user code runs here, so a panicking
+    // comparator leaves the colony untouched.
+    fn permute(&mut self, mut order: Vec<usize>) {
+        for start in 0..order.len() {
+            let mut current = start;
+            while order[current] != start {
+                let next = order[current];
+                self.elements.swap(current, next);
+                self.index.swap(current, next);
+                order[current] = current;
+                current = next;
+            }
+            order[current] = current;
+        }
+        self.validate();
+    }
+
+    /// Verifies the id lookup tables are consistent with each other and with the elements,
+    /// see [ColonyIndex::check_invariants].
+    /// With the `debug-validate` feature, this runs after every mutation in debug builds.
+    pub fn check_invariants(&self) -> Result<(), &'static str> {
+        self.index.check_invariants()?;
+        if self.index.index_to_id.len() != self.elements.len() {
+            return Err("member count does not match element count");
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn validate(&self) {
+        #[cfg(all(feature = "debug-validate", debug_assertions))]
+        if let Err(error) = self.check_invariants() {
+            panic!("Colony invariant violated: {error}");
+        }
+    }
+
+    /// Number of elements the colony can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.elements.capacity()
+    }
+
+    /// Reserves capacity for at least `additional` more elements.
+    /// # Panics
+    /// Panics if the new capacity exceeds isize::MAX bytes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.index.reserve(additional);
+        self.elements.reserve(additional);
+    }
+
+    /// Shrinks the capacity as much as possible.
+    /// Freed ids still take up space in the lookup tables, see [Colony::compact].
+    pub fn shrink_to_fit(&mut self) {
+        self.index.shrink_to_fit();
+        self.elements.shrink_to_fit();
+    }
+
+    /// Re-assigns ids densely, so the id of each element becomes its packed index,
+    /// freed ids are forgotten and the lookup tables shrink to fit.
+    /// Returns a table mapping each old id to its new id, `None` for freed ids,
+    /// so stored ids can be fixed up.
+    /// ```rust
+    /// # use packed_colony::Colony;
+    /// let mut world = Colony::new();
+    /// let ids = world.extend_with_ids(0..100);
+    /// world.retain(|x| *x >= 90);
+    /// let remap = world.compact();
+    /// assert_eq!(world.slot_count(), 10);
+    /// assert_eq!(remap[ids[0]], None);
+    /// let new_id = remap[ids[95]].unwrap();
+    /// assert_eq!(world[new_id], 95);
+    /// ```
+    pub fn compact(&mut self) -> Vec<Option<usize>> {
+        let remap = self.index.compact();
+        self.validate();
+        remap
+    }
+
+    /// Number of ids handed out so far, live or freed.
+    /// The id lookup table has this many entries.
+    pub fn slot_count(&self) -> usize {
+        self.index.slot_count()
+    }
+
+    /// Number of freed ids waiting to be re-used.
+    /// A high count relative to [Colony::slot_count] means a fragmented id space.
+    pub fn freed_count(&self) -> usize {
+        self.index.freed_count()
+    }
+
+    /// Removes all elements, yielding them in packed order.
+    /// The colony is left empty, but keeps its allocated capacity.
+    /// If the iterator is dropped early, the remaining elements are dropped.
+    pub fn drain(&mut self) -> alloc::vec::Drain<'_, T> {
+        self.index.clear();
+        self.elements.drain(..)
+    }
+
+    /// Like [Colony::drain], but yields `(id, element)`.
+    pub fn drain_with_ids(&mut self) -> impl ExactSizeIterator<Item = (usize, T)> {
+        self.index.drain().zip(self.elements.drain(..))
     }
 
     pub const fn as_slice(&self) -> &[T] {
@@ -206,7 +730,7 @@ impl<T> Colony<T> {
     }
 }
 
-impl<T> std::ops::Index<usize> for Colony<T> {
+impl<T, I: IndexType> core::ops::Index<usize> for Colony<T, I> {
     type Output = T;
 
     fn index(&self, id: usize) -> &Self::Output {
@@ -214,13 +738,13 @@ impl<T> std::ops::Index<usize> for Colony<T> {
     }
 }
 
-impl<T> std::ops::IndexMut<usize> for Colony<T> {
+impl<T, I: IndexType> core::ops::IndexMut<usize> for Colony<T, I> {
     fn index_mut(&mut self, id: usize) -> &mut Self::Output {
         self.elements.index_mut(self.index.to_index_unchecked(id))
     }
 }
 
-impl<T> Deref for Colony<T> {
+impl<T, I> Deref for Colony<T, I> {
     type Target = [T];
 
     fn deref(&self) -> &Self::Target {
@@ -228,24 +752,61 @@ impl<T> Deref for Colony<T> {
     }
 }
 
-impl<T> DerefMut for Colony<T> {
+impl<T, I> DerefMut for Colony<T, I> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.elements.deref_mut()
     }
 }
 
-impl<T> IntoIterator for Colony<T> {
+impl<T, I> IntoIterator for Colony<T, I> {
     type Item = T;
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+    type IntoIter = alloc::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.elements.into_iter()
     }
 }
 
+impl<T, I: IndexType> Extend<T> for Colony<T, I> {
+    fn extend<It: IntoIterator<Item = T>>(&mut self, iter: It) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for element in iter {
+            self.insert(element);
+        }
+    }
+}
+
+impl<T, I: IndexType> FromIterator<T> for Colony<T, I> {
+    fn from_iter<It: IntoIterator<Item = T>>(iter: It) -> Self {
+        let mut colony = Self::new_with_index_type();
+        colony.extend(iter);
+        colony
+    }
+}
+
+impl<'a, T, I> IntoIterator for &'a Colony<T, I> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter()
+    }
+}
+
+impl<'a, T, I> IntoIterator for &'a mut Colony<T, I> {
+    type Item = &'a mut T;
+    type IntoIter = core::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter_mut()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use core::iter::FusedIterator;
 
     #[test]
     fn basic() {
@@ -275,12 +836,311 @@ mod tests {
         assert_eq!(world[c], "C");
         assert_eq!(world.len(), 3);
         assert_eq!(world.get(1337), None);
-        world.remove(1337);
+        assert_eq!(world.remove(1337), None);
         assert_eq!(*world.get(a).unwrap(), "A");
-        world.remove(a);
+        assert_eq!(world.remove(a), Some("A"));
         assert_eq!(world.get(a), None);
-        world.remove(a);
+        assert_eq!(world.remove(a), None);
         world.clear();
         world.clear();
     }
+
+    #[test]
+    fn invariants() {
+        let mut world = Colony::new();
+        world.check_invariants().unwrap();
+        let ids = world.extend_with_ids(0..20);
+        for id in ids.iter().step_by(3) {
+            world.remove(*id);
+        }
+        world.check_invariants().unwrap();
+        world.sort_by_key(|x| core::cmp::Reverse(*x));
+        world.check_invariants().unwrap();
+
+        let mut broken = world.clone();
+        broken.elements.pop();
+        assert!(broken.check_invariants().is_err());
+        let mut broken = world.clone();
+        broken.index.index_to_id.swap(0, 1);
+        assert!(broken.check_invariants().is_err());
+        let mut broken = world.clone();
+        broken.index.freed[0] = broken.index.freed[1];
+        assert!(broken.check_invariants().is_err());
+        let mut broken = world.clone();
+        broken.index.freed[0] = ids[1];
+        assert!(broken.check_invariants().is_err());
+        let mut broken = world;
+        broken.index.id_to_index.push(0);
+        assert!(broken.check_invariants().is_err());
+    }
+
+    #[test]
+    fn remove_last() {
+        let mut world = Colony::new();
+        let a = world.insert("A");
+        let b = world.insert("B");
+        assert_eq!(world.remove(b), Some("B"));
+        assert_eq!(world.remove(b), None);
+        assert_eq!(world[a], "A");
+        let c = world.insert("C");
+        assert_eq!(world[c], "C");
+        assert_eq!(world.len(), 2);
+    }
+
+    #[test]
+    fn remove_empty() {
+        let mut world = Colony::new();
+        assert_eq!(world.remove(0), None);
+        let a = world.insert("A");
+        assert_eq!(world.remove(a), Some("A"));
+        assert_eq!(world.remove(a), None);
+        world.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn drain() {
+        let mut world = Colony::with_capacity(8);
+        let a = world.insert(1);
+        let b = world.insert(2);
+        let c = world.insert(3);
+        world.remove(b);
+        let mut drained: Vec<_> = world.drain_with_ids().collect();
+        drained.sort();
+        assert_eq!(drained, [(a, 1), (c, 3)]);
+        assert!(world.is_empty());
+        assert_eq!(world.get(a), None);
+        assert!(world.elements.capacity() >= 8);
+
+        let a = world.insert(4);
+        world.insert(5);
+        assert_eq!(a, 0);
+        assert_eq!(world.drain().sum::<i32>(), 9);
+        assert!(world.is_empty());
+        assert_eq!(world.get(a), None);
+        assert_eq!(world.insert(6), 0);
+    }
+
+    #[test]
+    fn insert_with() {
+        let mut world = Colony::new();
+        let a = world.insert_with(|id| id * 10);
+        let b = world.insert_with(|id| id * 10);
+        assert_eq!(world[a], a * 10);
+        assert_eq!(world[b], b * 10);
+        world.remove(a);
+        let c = world.insert_with(|id| id * 10);
+        assert_eq!(world[c], c * 10);
+
+        let (id, value) = world.get_or_insert_with(b, |_| unreachable!());
+        assert_eq!((id, *value), (b, b * 10));
+        *value = 7;
+        assert_eq!(world[b], 7);
+        let (id, value) = world.get_or_insert_with(1337, |id| id + 1);
+        assert_eq!(*value, id + 1);
+        assert_ne!(id, 1337);
+        assert_eq!(world.len(), 3);
+    }
+
+    #[test]
+    fn sort() {
+        let mut world = Colony::new();
+        let values = [5, 3, 9, 1, 7, 3, 0, 8];
+        let ids: Vec<usize> = values.iter().map(|x| world.insert(*x)).collect();
+        world.remove(ids[2]);
+        world.sort_by_key(|x| *x);
+        assert_eq!(world.as_slice(), [0, 1, 3, 3, 5, 7, 8]);
+        for (id, value) in ids.iter().zip(values) {
+            if *id != ids[2] {
+                assert_eq!(world[*id], value);
+            }
+        }
+        world.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(world.as_slice(), [8, 7, 5, 3, 3, 1, 0]);
+        for (id, value) in ids.iter().zip(values) {
+            assert_eq!(world.get(*id), (*id != ids[2]).then_some(&value));
+        }
+        world.sort();
+        assert_eq!(world.as_slice(), [0, 1, 3, 3, 5, 7, 8]);
+        for (id, value) in ids.iter().zip(values) {
+            assert_eq!(world.get(*id), (*id != ids[2]).then_some(&value));
+        }
+        world.sort_unstable_by_key(|x| core::cmp::Reverse(*x));
+        world.sort_unstable();
+        assert_eq!(world.as_slice(), [0, 1, 3, 3, 5, 7, 8]);
+        for (id, value) in ids.iter().zip(values) {
+            assert_eq!(world.get(*id), (*id != ids[2]).then_some(&value));
+        }
+        world.sort_by_cached_key(|x| core::cmp::Reverse(*x));
+        assert_eq!(world.as_slice(), [8, 7, 5, 3, 3, 1, 0]);
+        for (id, value) in ids.iter().zip(values) {
+            assert_eq!(world.get(*id), (*id != ids[2]).then_some(&value));
+        }
+        let id = world.insert(4);
+        assert_eq!(world[id], 4);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut world = Colony::new();
+        world.reserve(100);
+        assert!(world.capacity() >= 100);
+        let ids: Vec<usize> = (0..10).map(|x| world.insert(x)).collect();
+        for id in &ids[..4] {
+            world.remove(*id);
+        }
+        assert_eq!(world.slot_count(), 10);
+        assert_eq!(world.freed_count(), 4);
+        world.shrink_to_fit();
+        assert!(world.capacity() < 100);
+        world.insert(10);
+        assert_eq!(world.slot_count(), 10);
+        assert_eq!(world.freed_count(), 3);
+        world.clear();
+        assert_eq!(world.slot_count(), 0);
+        assert_eq!(world.freed_count(), 0);
+    }
+
+    #[test]
+    fn u32_index() {
+        let mut world: Colony<&str, u32> = Colony::new_with_index_type();
+        let a = world.insert("A");
+        let b = world.insert("B");
+        assert_eq!(world.remove(a), Some("A"));
+        assert_eq!(world.get(a), None);
+        assert_eq!(world[b], "B");
+        assert_eq!(world.insert("C"), a);
+        assert_eq!(
+            u32::try_from_usize(u32::MAX as usize - 1),
+            Some(u32::MAX - 1)
+        );
+        assert_eq!(u32::try_from_usize(u32::MAX as usize), None);
+        assert_eq!(u32::try_from_usize(usize::MAX), None);
+    }
+
+    #[test]
+    fn into_iter() {
+        fn assert_iter<I: ExactSizeIterator + DoubleEndedIterator + FusedIterator>(iter: I) -> I {
+            iter
+        }
+        let mut world = Colony::new();
+        world.insert(1);
+        world.insert(2);
+        world.insert(3);
+        for x in &mut world {
+            *x *= 10;
+        }
+        let mut sum = 0;
+        for x in &world {
+            sum += x;
+        }
+        assert_eq!(sum, 60);
+        assert_eq!(assert_iter((&world).into_iter()).last(), Some(&30));
+        assert_eq!(assert_iter((&mut world).into_iter()).len(), 3);
+        assert_eq!(assert_iter(world.into_iter()).next_back(), Some(30));
+    }
+
+    #[test]
+    fn extend() {
+        let mut world: Colony<i32> = (0..5).collect();
+        assert_eq!(world.as_slice(), [0, 1, 2, 3, 4]);
+        world.remove(2);
+        world.extend([5, 6]);
+        assert_eq!(world.len(), 6);
+        let ids = world.extend_with_ids([7, 8, 9]);
+        assert_eq!(ids.len(), 3);
+        for (id, value) in ids.into_iter().zip(7..) {
+            assert_eq!(world[id], value);
+        }
+    }
+
+    #[test]
+    fn unchecked() {
+        let mut world = Colony::new();
+        let ids = world.extend_with_ids([1, 2, 3]);
+        world.remove(ids[0]);
+        assert!(!world.contains_id(ids[0]));
+        assert!(!world.contains_id(1337));
+        for id in &ids[1..] {
+            assert!(world.contains_id(*id));
+            // SAFETY: Checked by contains_id above.
+            unsafe { *world.get_unchecked_mut(*id) *= 10 };
+        }
+        // SAFETY: ids[1] and ids[2] were not removed.
+        unsafe {
+            assert_eq!(*world.get_unchecked(ids[1]), 20);
+            assert_eq!(*world.get_unchecked(ids[2]), 30);
+        }
+    }
+
+    #[test]
+    fn compact() {
+        let mut world = Colony::new();
+        let ids = world.extend_with_ids(0..10);
+        for id in ids.iter().step_by(3) {
+            world.remove(*id);
+        }
+        let remap = world.compact();
+        assert_eq!(remap.len(), 10);
+        assert_eq!(world.slot_count(), 6);
+        assert_eq!(world.freed_count(), 0);
+        for (value, id) in ids.iter().enumerate() {
+            match remap[*id] {
+                Some(new_id) => assert_eq!(world[new_id], value),
+                None => assert_eq!(value % 3, 0),
+            }
+        }
+        assert_eq!(world.insert(10), 6);
+    }
+
+    #[test]
+    fn remove_with() {
+        let mut world = Colony::new();
+        let a = world.insert("A");
+        let b = world.insert("B");
+        let c = world.insert("C");
+        let mut moves = Vec::new();
+        world.remove_with(a, |id, index| moves.push((id, index)));
+        assert_eq!(moves, [(c, 0)]);
+        assert_eq!(world.as_slice()[0], "C");
+        world.remove_with(b, |id, index| moves.push((id, index)));
+        assert_eq!(world.remove_with(a, |_, _| unreachable!()), None);
+        assert_eq!(moves, [(c, 0)]);
+    }
+
+    #[test]
+    fn retain() {
+        let mut world = Colony::new();
+        let ids: Vec<usize> = (0..10).map(|x| world.insert(x)).collect();
+        world.retain(|x| *x % 2 == 0);
+        assert_eq!(world.len(), 5);
+        for (id, x) in ids.iter().zip(0..10) {
+            assert_eq!(world.get(*id), (x % 2 == 0).then_some(&x));
+        }
+        world.retain_with_ids(|id, x| {
+            assert_eq!(id, ids[*x]);
+            *x *= 10;
+            id != ids[4]
+        });
+        assert_eq!(world.len(), 4);
+        assert_eq!(world.get(ids[4]), None);
+        assert_eq!(world[ids[8]], 80);
+        world.retain(|_| false);
+        assert!(world.is_empty());
+        let id = world.insert(7);
+        assert_eq!(world[id], 7);
+    }
+
+    #[test]
+    fn remove_retained() {
+        let mut world = Colony::new();
+        let a = world.insert(1);
+        world.retain(|_| false);
+        assert_eq!(world.get(a), None);
+        assert_eq!(world.remove(a), None);
+        let b = world.insert(2);
+        let c = world.insert(3);
+        world.retain(|x| *x != 3);
+        assert_eq!(world.remove(c), None);
+        assert_eq!(world[b], 2);
+    }
 }
@@ -0,0 +1,82 @@
+use crate::{Colony, IndexType};
+use rayon::prelude::*;
+
+impl<T: Send, I> IntoParallelIterator for Colony<T, I> {
+    type Item = T;
+    type Iter = rayon::vec::IntoIter<T>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.elements.into_par_iter()
+    }
+}
+
+impl<'a, T: Sync, I> IntoParallelIterator for &'a Colony<T, I> {
+    type Item = &'a T;
+    type Iter = rayon::slice::Iter<'a, T>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.elements.par_iter()
+    }
+}
+
+impl<'a, T: Send, I> IntoParallelIterator for &'a mut Colony<T, I> {
+    type Item = &'a mut T;
+    type Iter = rayon::slice::IterMut<'a, T>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.elements.par_iter_mut()
+    }
+}
+
+impl<T, I: IndexType> Colony<T, I> {
+    /// Parallel iterator over `(id, &element)` in packed order.
+    pub fn par_iter_with_ids(&self) -> impl IndexedParallelIterator<Item = (usize, &T)>
+    where
+        T: Sync,
+    {
+        self.index
+            .index_to_id
+            .par_iter()
+            .map(|id| id.to_usize())
+            .zip(self.elements.par_iter())
+    }
+
+    /// Parallel iterator over `(id, &mut element)` in packed order.
+    pub fn par_iter_mut_with_ids(&mut self) -> impl IndexedParallelIterator<Item = (usize, &mut T)>
+    where
+        T: Send,
+    {
+        self.index
+            .index_to_id
+            .par_iter()
+            .map(|id| id.to_usize())
+            .zip(self.elements.par_iter_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Colony;
+    use alloc::vec::Vec;
+    use rayon::prelude::*;
+
+    #[test]
+    fn parallel() {
+        let mut world = Colony::new();
+        let ids: Vec<usize> = (0..1000).map(|x| world.insert(x)).collect();
+        world.remove(ids[10]);
+        world.par_iter_mut().for_each(|x| *x *= 2);
+        assert_eq!(
+            world.par_iter().sum::<usize>(),
+            (0..1000).sum::<usize>() * 2 - 20
+        );
+        world
+            .par_iter_with_ids()
+            .for_each(|(id, x)| assert_eq!(*x, ids.iter().position(|i| *i == id).unwrap() * 2));
+        world.par_iter_mut_with_ids().for_each(|(id, x)| *x = id);
+        for id in &ids[11..] {
+            assert_eq!(world[*id], *id);
+        }
+        assert_eq!(world.into_par_iter().count(), 999);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::{Colony, IndexType};
+use alloc::vec::Vec;
+use core::{
+    cmp::Ordering,
+    ops::{Deref, DerefMut},
+};
+
+#[derive(Debug, Clone)]
+/// # SecondaryColony
+/// Packed storage for extra per-element data, keyed by the ids of a primary [Colony].
+/// ```rust
+/// # use packed_colony::{Colony, SecondaryColony};
+/// let mut names = Colony::new();
+/// let mut ages = SecondaryColony::new();
+/// let alice = names.insert("Alice");
+/// let bob = names.insert("Bob");
+/// ages.insert(alice, 34);
+/// for (id, name, age) in ages.zip_by_id(&names) {
+///     println!("{id}: {name} is {age}");
+/// }
+/// assert_eq!(ages.get(bob), None);
+/// ```
+/// Like a [Colony], the data is tightly packed and lookup is two array accesses,
+/// but the ids are chosen by the caller instead of handed out.
+/// Ids are re-used by the primary [Colony], so remove an id from the
+/// secondary when removing it from the primary.
+pub struct SecondaryColony<T> {
+    // ID -> Member Index
+    id_to_index: Vec<usize>,
+    // Member Index -> ID
+    index_to_id: Vec<usize>,
+    elements: Vec<T>,
+}
+
+impl<T> Default for SecondaryColony<T> {
+    fn default() -> Self {
+        Self {
+            id_to_index: Vec::new(),
+            index_to_id: Vec::new(),
+            elements: Vec::new(),
+        }
+    }
+}
+
+impl<T> SecondaryColony<T> {
+    pub fn new() -> Self {
+        SecondaryColony::default()
+    }
+
+    /// Constructs a new, empty SecondaryColony<T> with at least the specified capacity.
+    /// # Panics
+    /// Panics if the new capacity exceeds isize::MAX bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            id_to_index: Vec::with_capacity(capacity),
+            index_to_id: Vec::with_capacity(capacity),
+            elements: Vec::with_capacity(capacity),
+        }
+    }
+
+    fn to_index(&self, id: usize) -> Option<usize> {
+        let index = *self.id_to_index.get(id).unwrap_or(&usize::MAX);
+        if index == usize::MAX {
+            return None;
+        }
+        Some(index)
+    }
+
+    /// Inserts `value` for `id`, returning the previous value if there was one.
+    /// # Panics
+    /// Panics if `id` is `usize::MAX`, which no [Colony] hands out.
+    pub fn insert(&mut self, id: usize, value: T) -> Option<T> {
+        assert!(id != usize::MAX, "SecondaryColony id out of range");
+        if let Some(index) = self.to_index(id) {
+            return Some(core::mem::replace(&mut self.elements[index], value));
+        }
+        if id >= self.id_to_index.len() {
+            self.id_to_index.resize(id + 1, usize::MAX);
+        }
+        self.id_to_index[id] = self.elements.len();
+        self.index_to_id.push(id);
+        self.elements.push(value);
+        None
+    }
+
+    pub fn contains_id(&self, id: usize) -> bool {
+        self.to_index(id).is_some()
+    }
+
+    /// The Index trait is also supported.
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.elements.get(self.to_index(id)?)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut T> {
+        let index = self.to_index(id)?;
+        self.elements.get_mut(index)
+    }
+
+    /// Removes the value for `id` and returns it.
+    /// Idempotent, calling with an absent id will do nothing and return `None`.
+    pub fn remove(&mut self, id: usize) -> Option<T> {
+        let index = self.to_index(id)?;
+        self.id_to_index[id] = usize::MAX;
+        self.index_to_id.swap_remove(index);
+        if let Some(&moved_id) = self.index_to_id.get(index) {
+            self.id_to_index[moved_id] = index;
+        }
+        Some(self.elements.swap_remove(index))
+    }
+
+    pub fn clear(&mut self) {
+        self.id_to_index.clear();
+        self.index_to_id.clear();
+        self.elements.clear();
+    }
+
+    /// Iterates over `(id, &value)` in packed order.
+    pub fn iter_with_ids(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.index_to_id.iter().copied().zip(self.elements.iter())
+    }
+
+    /// Iterates over `(id, &mut value)` in packed order.
+    pub fn iter_mut_with_ids(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        self.index_to_id
+            .iter()
+            .copied()
+            .zip(self.elements.iter_mut())
+    }
+
+    /// Iterates over `(id, &primary, &value)` for every id present in both colonies.
+    pub fn zip_by_id<'a, P, I: IndexType>(
+        &'a self,
+        primary: &'a Colony<P, I>,
+    ) -> impl Iterator<Item = (usize, &'a P, &'a T)> {
+        self.iter_with_ids()
+            .filter_map(|(id, value)| Some((id, primary.get(id)?, value)))
+    }
+
+    /// Iterates over `(id, &primary, &mut value)` for every id present in both colonies.
+    pub fn zip_by_id_mut<'a, P, I: IndexType>(
+        &'a mut self,
+        primary: &'a Colony<P, I>,
+    ) -> impl Iterator<Item = (usize, &'a P, &'a mut T)> {
+        self.iter_mut_with_ids()
+            .filter_map(|(id, value)| Some((id, primary.get(id)?, value)))
+    }
+
+    /// Sorts the values, ids keep referring to the same values.
+    /// Shadows [slice::sort], which would leave ids pointing at the wrong values.
+    /// This sort is stable.
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_by(Ord::cmp);
+    }
+
+    /// Like [SecondaryColony::sort], but may not preserve the order of equal values.
+    pub fn sort_unstable(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_unstable_by(Ord::cmp);
+    }
+
+    /// Sorts the values with a comparator, ids keep referring to the same values.
+    /// This sort is stable.
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut order: Vec<usize> = (0..self.elements.len()).collect();
+        order.sort_by(|a, b| compare(&self.elements[*a], &self.elements[*b]));
+        self.permute(order);
+    }
+
+    /// Sorts the values with a key extraction function, see [SecondaryColony::sort_by].
+    pub fn sort_by_key<K, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    /// Like [SecondaryColony::sort_by_key], but calls `f` only once per value.
+    /// This sort is stable.
+    pub fn sort_by_cached_key<K, F>(&mut self, f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        let keys: Vec<K> = self.elements.iter().map(f).collect();
+        let mut order: Vec<usize> = (0..self.elements.len()).collect();
+        order.sort_by(|a, b| keys[*a].cmp(&keys[*b]));
+        self.permute(order);
+    }
+
+    /// Like [SecondaryColony::sort_by], but may not preserve the order of equal values.
+    pub fn sort_unstable_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut order: Vec<usize> = (0..self.elements.len()).collect();
+        order.sort_unstable_by(|a, b| compare(&self.elements[*a], &self.elements[*b]));
+        self.permute(order);
+    }
+
+    /// Like [SecondaryColony::sort_by_key], but may not preserve the order of equal values.
+    pub fn sort_unstable_by_key<K, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_unstable_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    // Moves the value at index order[i] to index i, see Colony::permute.
+    fn permute(&mut self, mut order: Vec<usize>) {
+        for start in 0..order.len() {
+            let mut current = start;
+            while order[current] != start {
+                let next = order[current];
+                self.elements.swap(current, next);
+                self.index_to_id.swap(current, next);
+                self.id_to_index[self.index_to_id[current]] = current;
+                self.id_to_index[self.index_to_id[next]] = next;
+                order[current] = current;
+                current = next;
+            }
+            order[current] = current;
+        }
+    }
+
+    pub const fn as_slice(&self) -> &[T] {
+        self.elements.as_slice()
+    }
+
+    pub const fn as_mut_slice(&mut self) -> &mut [T] {
+        self.elements.as_mut_slice()
+    }
+}
+
+impl<T> core::ops::Index<usize> for SecondaryColony<T> {
+    type Output = T;
+
+    fn index(&self, id: usize) -> &Self::Output {
+        self.elements.index(self.id_to_index[id])
+    }
+}
+
+impl<T> core::ops::IndexMut<usize> for SecondaryColony<T> {
+    fn index_mut(&mut self, id: usize) -> &mut Self::Output {
+        self.elements.index_mut(self.id_to_index[id])
+    }
+}
+
+impl<T> Deref for SecondaryColony<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.elements.deref()
+    }
+}
+
+impl<T> DerefMut for SecondaryColony<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.elements.deref_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secondary() {
+        let mut names = Colony::new();
+        let mut ages = SecondaryColony::new();
+        let a = names.insert("A");
+        let b = names.insert("B");
+        let c = names.insert("C");
+        assert_eq!(ages.insert(c, 3), None);
+        assert_eq!(ages.insert(a, 1), None);
+        assert_eq!(ages.insert(a, 10), Some(1));
+        assert_eq!(ages.len(), 2);
+        assert_eq!(ages[a], 10);
+        assert_eq!(ages.get(b), None);
+        assert!(!ages.contains_id(1337));
+        // Slice methods stay reachable through Deref.
+        assert!(ages.contains(&10));
+        assert_eq!(ages.iter().sum::<i32>(), 13);
+        let mut pairs: Vec<_> = ages.iter_with_ids().collect();
+        pairs.sort();
+        assert_eq!(pairs, [(a, &10), (c, &3)]);
+
+        names.remove(c);
+        let zipped: Vec<_> = ages.zip_by_id(&names).collect();
+        assert_eq!(zipped, [(a, &"A", &10)]);
+        for (_, _, age) in ages.zip_by_id_mut(&names) {
+            *age += 1;
+        }
+        assert_eq!(ages[a], 11);
+
+        assert_eq!(ages.remove(a), Some(11));
+        assert_eq!(ages.remove(a), None);
+        assert_eq!(ages[c], 3);
+        assert_eq!(ages.remove(c), Some(3));
+        assert!(ages.is_empty());
+    }
+
+    #[test]
+    fn insert_max_id() {
+        let mut ages = SecondaryColony::new();
+        ages.insert(0, 1);
+        ages.insert(3, 4);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            ages.insert(usize::MAX, 5);
+        }));
+        assert!(result.is_err());
+        assert_eq!(ages.len(), 2);
+        assert_eq!(ages.get(0), Some(&1));
+        assert_eq!(ages.get(3), Some(&4));
+        assert_eq!(ages.get(usize::MAX), None);
+    }
+
+    #[test]
+    fn sort() {
+        let mut ages = SecondaryColony::new();
+        let values = [5, 3, 9, 1, 7, 3, 0, 8];
+        // Sparse ids, in no particular order.
+        let ids = [4, 12, 0, 7, 3, 9, 1, 20];
+        for (id, value) in ids.iter().zip(values) {
+            ages.insert(*id, value);
+        }
+        ages.remove(ids[2]);
+        let check = |ages: &SecondaryColony<i32>| {
+            for (id, value) in ids.iter().zip(values) {
+                assert_eq!(ages.get(*id), (*id != ids[2]).then_some(&value));
+            }
+        };
+        ages.sort();
+        assert_eq!(ages.as_slice(), [0, 1, 3, 3, 5, 7, 8]);
+        check(&ages);
+        ages.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(ages.as_slice(), [8, 7, 5, 3, 3, 1, 0]);
+        check(&ages);
+        ages.sort_by_key(|x| *x % 4);
+        check(&ages);
+        ages.sort_unstable();
+        assert_eq!(ages.as_slice(), [0, 1, 3, 3, 5, 7, 8]);
+        check(&ages);
+        ages.sort_by_cached_key(|x| core::cmp::Reverse(*x));
+        assert_eq!(ages.as_slice(), [8, 7, 5, 3, 3, 1, 0]);
+        check(&ages);
+        ages.sort_unstable_by_key(|x| *x);
+        ages.sort_by(|a, b| b.cmp(a));
+        check(&ages);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{Colony, ColonyIndex, IndexType};
+use alloc::{vec, vec::Vec};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+/// Bumped whenever the serialized layout changes.
+const VERSION: u32 = 1;
+
+// Only the packed ids and the freed stack are stored,
+// `id_to_index` is rebuilt from them on deserialize.
+#[derive(Serialize)]
+#[serde(rename = "Colony", bound = "T: Serialize")]
+struct ColonyRef<'a, T, I: IndexType> {
+    version: u32,
+    elements: &'a [T],
+    ids: Ids<'a, I>,
+    freed: Ids<'a, I>,
+}
+
+// Ids are always stored as `usize`, whatever the index type.
+struct Ids<'a, I>(&'a [I]);
+
+impl<I: IndexType> Serialize for Ids<'_, I> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(|id| id.to_usize()))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Colony")]
+struct ColonyOwned<T> {
+    version: u32,
+    elements: Vec<T>,
+    ids: Vec<usize>,
+    freed: Vec<usize>,
+}
+
+impl<T: Serialize, I: IndexType> Serialize for Colony<T, I> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ColonyRef {
+            version: VERSION,
+            elements: &self.elements,
+            ids: Ids(&self.index.index_to_id),
+            freed: Ids(&self.index.freed),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>, I: IndexType> Deserialize<'de> for Colony<T, I> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let owned = ColonyOwned::deserialize(deserializer)?;
+        if owned.version != VERSION {
+            return Err(D::Error::custom(format_args!(
+                "unsupported Colony version {}, expected {VERSION}",
+                owned.version
+            )));
+        }
+        if owned.ids.len() != owned.elements.len() {
+            return Err(D::Error::custom("Colony ids and elements differ in length"));
+        }
+        // Every id ever handed out is either live or freed, exactly once.
+        let slots = owned.ids.len() + owned.freed.len();
+        if slots > 0 && I::try_from_usize(slots - 1).is_none() {
+            return Err(D::Error::custom(
+                "Colony has too many ids for its index type",
+            ));
+        }
+        let mut id_to_index = vec![I::MAX; slots];
+        for (index, &id) in owned.ids.iter().enumerate() {
+            match id_to_index.get_mut(id) {
+                Some(slot) if *slot == I::MAX => *slot = I::from_usize(index),
+                _ => return Err(D::Error::custom("Colony id out of range or duplicated")),
+            }
+        }
+        let mut seen = vec![false; slots];
+        for &id in &owned.freed {
+            match (id_to_index.get(id), seen.get_mut(id)) {
+                (Some(&index), Some(seen)) if index == I::MAX && !*seen => *seen = true,
+                _ => {
+                    return Err(D::Error::custom(
+                        "Colony freed id out of range or duplicated",
+                    ));
+                }
+            }
+        }
+        Ok(Colony {
+            index: ColonyIndex {
+                id_to_index,
+                index_to_id: owned.ids.into_iter().map(I::from_usize).collect(),
+                freed: owned.freed.into_iter().map(I::from_usize).collect(),
+            },
+            elements: owned.elements,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Colony;
+    use alloc::string::{String, ToString};
+
+    #[test]
+    fn round_trip() {
+        let mut world = Colony::new();
+        let a = world.insert("A".to_string());
+        let b = world.insert("B".to_string());
+        let c = world.insert("C".to_string());
+        world.remove(a);
+        let json = serde_json::to_string(&world).unwrap();
+        let mut loaded: Colony<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.get(a), None);
+        assert_eq!(loaded[b], "B");
+        assert_eq!(loaded[c], "C");
+        assert_eq!(loaded.as_slice(), world.as_slice());
+        assert_eq!(
+            loaded.insert("D".to_string()),
+            world.insert("D".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        let invalid = [
+            r#"{"version":2,"elements":[],"ids":[],"freed":[]}"#,
+            r#"{"version":1,"elements":[1],"ids":[],"freed":[]}"#,
+            r#"{"version":1,"elements":[1],"ids":[1],"freed":[]}"#,
+            r#"{"version":1,"elements":[1,2],"ids":[0,0],"freed":[]}"#,
+            r#"{"version":1,"elements":[1],"ids":[0],"freed":[0]}"#,
+            r#"{"version":1,"elements":[1],"ids":[0],"freed":[1,1]}"#,
+        ];
+        for json in invalid {
+            assert!(serde_json::from_str::<Colony<u8>>(json).is_err(), "{json}");
+        }
+        let valid = r#"{"version":1,"elements":[7],"ids":[1],"freed":[0]}"#;
+        let world: Colony<u8> = serde_json::from_str(valid).unwrap();
+        assert_eq!(world[1], 7);
+        assert_eq!(world.get(0), None);
+        let compact: Colony<u8, u32> = serde_json::from_str(valid).unwrap();
+        assert_eq!(compact[1], 7);
+        assert_eq!(serde_json::to_string(&compact).unwrap(), valid);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{Colony, ColonyIndex, IndexType};
+use core::marker::PhantomData;
+
+/// Iterator over `(id, &a, &b)` for ids present in two colonies.
+/// Created by [Colony::zip_by_id].
+pub struct ZipById<'a, A, B, IA = usize, IB = usize> {
+    a: &'a Colony<A, IA>,
+    b: &'a Colony<B, IB>,
+    position: usize,
+    // Iterate the smaller colony in packed order, looking up the other.
+    a_drives: bool,
+}
+
+impl<'a, A, B, IA: IndexType, IB: IndexType> ZipById<'a, A, B, IA, IB> {
+    pub(crate) fn new(a: &'a Colony<A, IA>, b: &'a Colony<B, IB>) -> Self {
+        Self {
+            a,
+            b,
+            position: 0,
+            a_drives: a.len() <= b.len(),
+        }
+    }
+}
+
+impl<'a, A, B, IA: IndexType, IB: IndexType> Iterator for ZipById<'a, A, B, IA, IB> {
+    type Item = (usize, &'a A, &'a B);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (a, b) = (self.a, self.b);
+        if self.a_drives {
+            while let Some(element) = a.elements.get(self.position) {
+                let id = a.index.to_id(self.position);
+                self.position += 1;
+                if let Some(other) = b.get(id) {
+                    return Some((id, element, other));
+                }
+            }
+        } else {
+            while let Some(other) = b.elements.get(self.position) {
+                let id = b.index.to_id(self.position);
+                self.position += 1;
+                if let Some(element) = a.get(id) {
+                    return Some((id, element, other));
+                }
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let driver = if self.a_drives {
+            self.a.len()
+        } else {
+            self.b.len()
+        };
+        (0, Some(driver - self.position))
+    }
+}
+
+/// Iterator over `(id, &mut a, &mut b)` for ids present in two colonies.
+/// Created by [Colony::zip_by_id_mut].
+pub struct ZipByIdMut<'a, A, B, IA = usize, IB = usize> {
+    a_index: &'a ColonyIndex<IA>,
+    a: *mut A,
+    a_len: usize,
+    b_index: &'a ColonyIndex<IB>,
+    b: *mut B,
+    b_len: usize,
+    position: usize,
+    a_drives: bool,
+    _elements: PhantomData<(&'a mut A, &'a mut B)>,
+}
+
+impl<'a, A, B, IA: IndexType, IB: IndexType> ZipByIdMut<'a, A, B, IA, IB> {
+    pub(crate) fn new(a: &'a mut Colony<A, IA>, b: &'a mut Colony<B, IB>) -> Self {
+        Self {
+            a_index: &a.index,
+            a: a.elements.as_mut_ptr(),
+            a_len: a.elements.len(),
+            b_index: &b.index,
+            b: b.elements.as_mut_ptr(),
+            b_len: b.elements.len(),
+            position: 0,
+            a_drives: a.elements.len() <= b.elements.len(),
+            _elements: PhantomData,
+        }
+    }
+}
+
+impl<'a, A, B, IA: IndexType, IB: IndexType> Iterator for ZipByIdMut<'a, A, B, IA, IB> {
+    type Item = (usize, &'a mut A, &'a mut B);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let driver_len = if self.a_drives {
+            self.a_len
+        } else {
+            self.b_len
+        };
+        while self.position < driver_len {
+            let position = self.position;
+            self.position += 1;
+            let (id, a_index, b_index) = if self.a_drives {
+                let id = self.a_index.to_id(position);
+                match self.b_index.to_index(id) {
+                    Some(b_index) => (id, position, b_index),
+                    None => continue,
+                }
+            } else {
+                let id = self.b_index.to_id(position);
+                match self.a_index.to_index(id) {
+                    Some(a_index) => (id, a_index, position),
+                    None => continue,
+                }
+            };
+            // SAFETY: Both indices are in bounds, as they map to live ids.
+            // The driving position only ever increases, and live ids map to
+            // distinct indices, so no element is handed out twice.
+            return Some(unsafe { (id, &mut *self.a.add(a_index), &mut *self.b.add(b_index)) });
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let driver_len = if self.a_drives {
+            self.a_len
+        } else {
+            self.b_len
+        };
+        (0, Some(driver_len - self.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Colony;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn zip_by_id() {
+        let mut positions = Colony::new();
+        let mut sprites = Colony::new();
+        for x in 0..10 {
+            positions.insert(x);
+            sprites.insert(x * 100);
+        }
+        for id in [1, 3, 4, 5, 6, 7, 8] {
+            sprites.remove(id);
+        }
+        positions.remove(0);
+        // The smaller colony drives, in both directions.
+        let mut zipped: Vec<_> = positions.zip_by_id(&sprites).collect();
+        zipped.sort();
+        assert_eq!(zipped, [(2, &2, &200), (9, &9, &900)]);
+        let mut zipped: Vec<_> = sprites.zip_by_id(&positions).map(|(id, _, _)| id).collect();
+        zipped.sort();
+        assert_eq!(zipped, [2, 9]);
+
+        for (id, position, sprite) in positions.zip_by_id_mut(&mut sprites) {
+            *position += 1;
+            *sprite += id;
+        }
+        for (id, sprite, position) in sprites.zip_by_id_mut(&mut positions) {
+            assert_eq!(*position, id + 1);
+            *sprite += 1;
+        }
+        assert_eq!(positions[2], 3);
+        assert_eq!(sprites[2], 203);
+        assert_eq!(sprites[9], 910);
+        assert_eq!(positions[1], 1);
+    }
+
+    #[test]
+    fn slice_join() {
+        // The slice method must stay reachable through Deref.
+        let names: Colony<&str> = ["A", "B"].into_iter().collect();
+        assert_eq!(names.join(","), "A,B");
+    }
+}